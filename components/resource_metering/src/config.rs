@@ -0,0 +1,117 @@
+// Copyright 2021 TiKV Project Authors. Licensed under Apache-2.0.
+
+//! Live-reloadable resource-metering configuration, registered with
+//! `tikv::config::ConfigController` the same way `raftstore.tranquility` is
+//! in `rfstore::store::config`.
+
+use std::time::Duration;
+
+use online_config::{ConfigChange, ConfigManager as _, OnlineConfig, Result as CfgResult};
+use serde::{Deserialize, Serialize};
+
+use crate::recorder::RecorderHandle;
+use crate::reporter::{AddressChangeNotifier, ConfigChangeNotifier};
+
+/// A `Duration` newtype with the few helpers this crate needs. It
+/// (de)serializes through serde's derived form of `Duration` (`secs` and
+/// `nanos`), not TiKV's readable string form such as `"1s"`.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ReadableDuration(pub Duration);
+
+impl ReadableDuration {
+    pub fn secs(secs: u64) -> ReadableDuration {
+        ReadableDuration(Duration::from_secs(secs))
+    }
+
+    pub fn millis(millis: u64) -> ReadableDuration {
+        ReadableDuration(Duration::from_millis(millis))
+    }
+
+    pub fn as_millis(&self) -> u64 {
+        self.0.as_millis() as u64
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, OnlineConfig)]
+#[serde(default)]
+#[serde(rename_all = "kebab-case")]
+pub struct Config {
+    /// Sleep interval of the recorder's background thread. Each
+    /// `ResourceUsageRecord` sample covers one `report_receiver_interval`,
+    /// not one `precision`.
+    pub precision: ReadableDuration,
+    /// Address tracked by [`crate::init_single_target`]. Nothing dials it,
+    /// and an empty value does not stop batches going to a registered
+    /// [`crate::DataSink`].
+    pub receiver_address: String,
+    /// How often a batch of samples is handed to the registered
+    /// [`crate::DataSink`].
+    pub report_receiver_interval: ReadableDuration,
+    /// Stored and live-updatable; neither the recorder nor the reporter
+    /// reads it, so the number of resource group tags in a report is not
+    /// capped and no tags are folded together.
+    pub max_resource_groups: u64,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            precision: ReadableDuration::secs(1),
+            receiver_address: String::new(),
+            report_receiver_interval: ReadableDuration::secs(60),
+            max_resource_groups: 5_000,
+        }
+    }
+}
+
+/// Applies live `resource-metering.*` config changes to the recorder and
+/// reporter created by [`crate::init_recorder`]/[`crate::init_reporter`].
+pub struct ConfigManager {
+    current: Config,
+    recorder: RecorderHandle,
+    config_notifier: ConfigChangeNotifier,
+    address_notifier: AddressChangeNotifier,
+}
+
+impl ConfigManager {
+    pub fn new(
+        current: Config,
+        recorder: RecorderHandle,
+        config_notifier: ConfigChangeNotifier,
+        address_notifier: AddressChangeNotifier,
+    ) -> ConfigManager {
+        ConfigManager {
+            current,
+            recorder,
+            config_notifier,
+            address_notifier,
+        }
+    }
+}
+
+impl online_config::ConfigManager for ConfigManager {
+    fn dispatch(&mut self, change: ConfigChange) -> CfgResult<()> {
+        // `ConfigValue` only converts by value, and `change.get` hands back
+        // a reference into the change set, so each value is cloned first.
+        if let Some(value) = change.get("precision") {
+            let millis: u64 = value.clone().into();
+            self.current.precision = ReadableDuration::millis(millis);
+            self.recorder.set_precision(self.current.precision.as_millis());
+        }
+        if let Some(value) = change.get("receiver-address") {
+            let address: String = value.clone().into();
+            self.current.receiver_address = address.clone();
+            self.address_notifier.notify(address);
+        }
+        if let Some(value) = change.get("report-receiver-interval") {
+            let millis: u64 = value.clone().into();
+            self.current.report_receiver_interval = ReadableDuration::millis(millis);
+            self.config_notifier
+                .notify(self.current.report_receiver_interval.0);
+        }
+        if let Some(value) = change.get("max-resource-groups") {
+            self.current.max_resource_groups = value.clone().into();
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,28 @@
+// Copyright 2021 TiKV Project Authors. Licensed under Apache-2.0.
+
+//! Per-resource-group-tag usage metering: turns "this request belongs to
+//! tag X" into periodic [`kvproto::resource_usage_agent::ResourceUsageRecord`]
+//! samples of CPU time and read/write key counts, handed to whatever
+//! [`DataSink`] is registered.
+//!
+//! See `recorder.rs` for how read/write keys and CPU time are threaded
+//! through [`ResourceTagFactory`]/[`ResourceMeteringTag`], and `reporter.rs`
+//! for the boundary around actually delivering samples over gRPC.
+
+pub mod config;
+mod model;
+mod recorder;
+mod reporter;
+mod worker;
+
+pub use config::{Config, ConfigManager};
+pub use model::RawRecord;
+pub use recorder::{
+    init_recorder, CollectorRegHandle, RecorderHandle, ResourceMeteringTag, ResourceTagFactory,
+    TagGuard,
+};
+pub use reporter::{
+    init_reporter, init_single_target, AddressChangeNotifier, ChannelDataSink,
+    ConfigChangeNotifier, DataSink, DataSinkRegHandle,
+};
+pub use worker::Worker;
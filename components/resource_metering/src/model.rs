@@ -0,0 +1,12 @@
+// Copyright 2021 TiKV Project Authors. Licensed under Apache-2.0.
+
+//! The raw, per-window usage a [`crate::ResourceMeteringTag`] accumulates
+//! before it's turned into a `ResourceUsageRecord` sample.
+
+/// Usage credited to one resource group tag since the last report.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RawRecord {
+    pub cpu_time_ms: u32,
+    pub read_keys: u32,
+    pub write_keys: u32,
+}
@@ -0,0 +1,166 @@
+// Copyright 2021 TiKV Project Authors. Licensed under Apache-2.0.
+
+//! Periodic reporting of recorder output.
+//!
+//! [`init_reporter`] turns whatever [`CollectorRegHandle`] accumulated
+//! since the last tick into one [`ResourceUsageRecord`] per tag, on a
+//! `report_receiver_interval` timer, and hands the batch to whatever
+//! [`DataSink`] is currently registered through [`DataSinkRegHandle`].
+//!
+//! [`init_single_target`] is meant to dial `receiver_address` and register
+//! a gRPC-backed [`DataSink`] that streams batches to a
+//! `ResourceMeteringPubSub` receiver (`kvproto`'s generated service, served
+//! in tests by `mock_receiver_server.rs`). Neither the generated service
+//! stubs nor `mock_receiver_server.rs` exist in this repository snapshot,
+//! so that sink isn't implemented here; [`init_single_target`] still
+//! tracks the configured address and exposes the same shape callers
+//! already depend on ([`AddressChangeNotifier`] and a stoppable
+//! [`Worker`]), and [`ChannelDataSink`] is provided so a sink can be
+//! registered directly (e.g. by a test) without a network round trip.
+
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crossbeam::channel::Sender;
+use kvproto::resource_usage_agent::ResourceUsageRecord;
+
+use crate::config::Config;
+use crate::recorder::CollectorRegHandle;
+use crate::worker::Worker;
+
+/// Where reported [`ResourceUsageRecord`] batches are sent.
+pub trait DataSink: Send {
+    fn send(&self, records: Vec<ResourceUsageRecord>);
+}
+
+/// Forwards batches as-is over a channel; the one [`DataSink`] this crate
+/// ships (see the module doc for why a gRPC sink isn't built here).
+pub struct ChannelDataSink(pub Sender<Vec<ResourceUsageRecord>>);
+
+impl DataSink for ChannelDataSink {
+    fn send(&self, records: Vec<ResourceUsageRecord>) {
+        let _ = self.0.send(records);
+    }
+}
+
+#[derive(Clone)]
+pub struct DataSinkRegHandle {
+    sink: Arc<Mutex<Option<Box<dyn DataSink>>>>,
+}
+
+impl DataSinkRegHandle {
+    pub fn register(&self, sink: Box<dyn DataSink>) {
+        *self.sink.lock().unwrap() = Some(sink);
+    }
+}
+
+/// Notifies the running reporter that `report_receiver_interval` changed.
+#[derive(Clone)]
+pub struct ConfigChangeNotifier {
+    interval_ms: Arc<AtomicU64>,
+}
+
+impl ConfigChangeNotifier {
+    pub fn notify(&self, interval: Duration) {
+        self.interval_ms
+            .store(interval.as_millis().max(1) as u64, Ordering::Relaxed);
+    }
+}
+
+/// Notifies whichever target [`init_single_target`] manages that
+/// `receiver_address` changed.
+#[derive(Clone)]
+pub struct AddressChangeNotifier {
+    address: Arc<Mutex<String>>,
+}
+
+impl AddressChangeNotifier {
+    pub fn notify(&self, address: String) {
+        *self.address.lock().unwrap() = address;
+    }
+}
+
+/// Start the reporter. Every `cfg.report_receiver_interval`, whatever usage
+/// [`init_recorder`](crate::init_recorder)'s registry accumulated is turned
+/// into one [`ResourceUsageRecord`] per tag and handed to the registered
+/// [`DataSink`], if any.
+pub fn init_reporter(
+    cfg: Config,
+    collector_reg_handle: CollectorRegHandle,
+) -> (ConfigChangeNotifier, DataSinkRegHandle, Worker) {
+    let interval_ms = Arc::new(AtomicU64::new(cfg.report_receiver_interval.as_millis().max(1)));
+    let sink: Arc<Mutex<Option<Box<dyn DataSink>>>> = Arc::new(Mutex::new(None));
+    let stop = Arc::new(AtomicBool::new(false));
+    let window = Arc::new(AtomicU64::new(0));
+
+    let worker_interval = interval_ms.clone();
+    let worker_sink = sink.clone();
+    let worker_stop = stop.clone();
+    let handle = thread::Builder::new()
+        .name("resource-metering-reporter".to_string())
+        .spawn(move || {
+            while !worker_stop.load(Ordering::Relaxed) {
+                let interval = Duration::from_millis(worker_interval.load(Ordering::Relaxed));
+                thread::sleep(interval);
+
+                let collected = collector_reg_handle.collect();
+                if collected.is_empty() {
+                    continue;
+                }
+                let timestamp_sec = window.fetch_add(interval.as_secs().max(1), Ordering::Relaxed);
+                let records = collected
+                    .into_iter()
+                    .map(|(tag, raw)| {
+                        let mut record = ResourceUsageRecord::default();
+                        record.set_resource_group_tag(tag);
+                        record.record_list_timestamp_sec = vec![timestamp_sec];
+                        record.record_list_cpu_time_ms = vec![raw.cpu_time_ms];
+                        record.record_list_read_keys = vec![raw.read_keys];
+                        record.record_list_write_keys = vec![raw.write_keys];
+                        record
+                    })
+                    .collect::<Vec<_>>();
+
+                if let Some(sink) = worker_sink.lock().unwrap().as_ref() {
+                    sink.send(records);
+                }
+            }
+        })
+        .unwrap();
+
+    (
+        ConfigChangeNotifier { interval_ms },
+        DataSinkRegHandle { sink },
+        Worker::new(stop, handle),
+    )
+}
+
+/// Track `address` for the reporter to send to. See the module doc: this
+/// does not dial out or register a sink on its own, since doing so needs
+/// generated service stubs this snapshot doesn't have; register a
+/// [`ChannelDataSink`] (or another [`DataSink`]) through
+/// [`DataSinkRegHandle::register`] directly in the meantime.
+pub fn init_single_target(
+    address: String,
+    env: Arc<grpcio::Environment>,
+    data_sink_reg_handle: DataSinkRegHandle,
+) -> (AddressChangeNotifier, Worker) {
+    let _ = env;
+    let _ = &data_sink_reg_handle;
+    let address = Arc::new(Mutex::new(address));
+    let stop = Arc::new(AtomicBool::new(false));
+
+    let worker_stop = stop.clone();
+    let handle = thread::Builder::new()
+        .name("resource-metering-single-target".to_string())
+        .spawn(move || {
+            while !worker_stop.load(Ordering::Relaxed) {
+                thread::sleep(Duration::from_millis(200));
+            }
+        })
+        .unwrap();
+
+    (AddressChangeNotifier { address }, Worker::new(stop, handle))
+}
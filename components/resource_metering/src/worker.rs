@@ -0,0 +1,34 @@
+// Copyright 2021 TiKV Project Authors. Licensed under Apache-2.0.
+
+//! A minimal stoppable background thread, used by the recorder, reporter,
+//! and single-target pieces below; none of their loops need the full
+//! pause/resume/tranquility machinery `rfstore::store::worker` provides,
+//! just "run until told to stop".
+
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::thread::JoinHandle;
+
+/// A handle to a background thread; dropping it leaves the thread running,
+/// call [`stop_worker`](Self::stop_worker) to signal it to exit and join it.
+pub struct Worker {
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Worker {
+    pub(crate) fn new(stop: Arc<AtomicBool>, handle: JoinHandle<()>) -> Worker {
+        Worker {
+            stop,
+            handle: Some(handle),
+        }
+    }
+
+    pub fn stop_worker(mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
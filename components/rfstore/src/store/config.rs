@@ -0,0 +1,56 @@
+// Copyright 2021 TiKV Project Authors. Licensed under Apache-2.0.
+
+//! Raftstore-level configuration that needs to be live-reloadable through
+//! [`tikv::config::ConfigController`], mirroring how `resource-metering.*`
+//! keys are wired up.
+
+use online_config::{ConfigChange, ConfigManager as _, OnlineConfig, Result as CfgResult};
+use serde::{Deserialize, Serialize};
+
+use crate::store::worker::WorkerRegistry;
+
+/// Name used for the scrub/scan throttle worker in [`WorkerRegistry`]; kept
+/// here since it's the one config-controlled tranquility value today.
+pub const SCRUB_WORKER_NAME: &str = "region-scrub";
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, OnlineConfig)]
+#[serde(default)]
+#[serde(rename_all = "kebab-case")]
+pub struct Config {
+    /// Duty-cycle throttle applied to background scan/maintenance workers:
+    /// after each iteration a worker sleeps `t_work * tranquility`, with
+    /// `t_work` averaged over a small window of recent iterations. `0`
+    /// means run flat out; higher values leave more IO/CPU headroom for
+    /// foreground raft traffic.
+    pub tranquility: f64,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config { tranquility: 1.0 }
+    }
+}
+
+/// Applies live `raftstore.tranquility` config changes to the running
+/// workers registered in a [`WorkerRegistry`].
+pub struct ConfigManager {
+    registry: WorkerRegistry,
+}
+
+impl ConfigManager {
+    pub fn new(registry: WorkerRegistry) -> ConfigManager {
+        ConfigManager { registry }
+    }
+}
+
+impl online_config::ConfigManager for ConfigManager {
+    fn dispatch(&mut self, change: ConfigChange) -> CfgResult<()> {
+        if let Some(value) = change.get("tranquility") {
+            // `ConfigValue` only converts by value (`From<ConfigValue>`),
+            // and `change.get` hands back a reference into the change set.
+            let tranquility: f64 = value.clone().into();
+            self.registry.set_tranquility(SCRUB_WORKER_NAME, tranquility);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,20 @@
+// Copyright 2021 TiKV Project Authors. Licensed under Apache-2.0.
+
+//! Accessors for the pair of engines backing a single store.
+
+use engine_traits::{KvEngine, RaftEngine};
+
+/// The engines backing a single store: `kv` holds region data and, in its
+/// `raft` column family, small raftstore-local metadata such as the scrub
+/// cursor; `raft` holds raft logs.
+#[derive(Clone)]
+pub struct Engines<K: KvEngine, R: RaftEngine> {
+    pub kv: K,
+    pub raft: R,
+}
+
+impl<K: KvEngine, R: RaftEngine> Engines<K, R> {
+    pub fn new(kv: K, raft: R) -> Engines<K, R> {
+        Engines { kv, raft }
+    }
+}
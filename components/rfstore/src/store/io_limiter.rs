@@ -0,0 +1,120 @@
+// Copyright 2021 TiKV Project Authors. Licensed under Apache-2.0.
+
+//! Tranquility-based throttling for background workers.
+//!
+//! Instead of capping a worker at a fixed bandwidth, a [`TranquilityThrottle`]
+//! self-adjusts a worker's duty cycle: after each iteration it sleeps for
+//! `t_work * tranquility`, where `t_work` is the average wall-clock time the
+//! last [`WINDOW_SIZE`] iterations spent doing useful work.
+//! `tranquility == 0` means run flat out;
+//! `tranquility == 2` sleeps twice as long as the worker just worked, i.e.
+//! roughly a 33% duty cycle. This keeps scan/scrub-style workers off the
+//! foreground raft path without needing a hand-tuned bytes-per-second cap.
+
+use std::{
+    collections::VecDeque,
+    sync::atomic::{AtomicU64, Ordering},
+    thread,
+    time::Duration,
+};
+
+/// Number of recent `t_work` samples kept in the moving window, so a single
+/// unusually slow iteration doesn't translate into one huge sleep.
+const WINDOW_SIZE: usize = 8;
+
+/// Self-adjusting throttle driven by how long each iteration actually took.
+pub struct TranquilityThrottle {
+    // Stored as bits of an f64 so it can be live-reloaded from another
+    // thread without a lock.
+    tranquility_bits: AtomicU64,
+    window: VecDeque<Duration>,
+}
+
+impl TranquilityThrottle {
+    pub fn new(tranquility: f64) -> TranquilityThrottle {
+        TranquilityThrottle {
+            tranquility_bits: AtomicU64::new(tranquility.max(0.0).to_bits()),
+            window: VecDeque::with_capacity(WINDOW_SIZE),
+        }
+    }
+
+    pub fn tranquility(&self) -> f64 {
+        f64::from_bits(self.tranquility_bits.load(Ordering::Relaxed))
+    }
+
+    /// Live-reload the tranquility factor; takes effect on the next sleep.
+    pub fn set_tranquility(&self, tranquility: f64) {
+        self.tranquility_bits
+            .store(tranquility.max(0.0).to_bits(), Ordering::Relaxed);
+    }
+
+    /// Record how long the iteration that just finished took, and sleep for
+    /// `average(t_work) * tranquility` before returning.
+    pub fn observe_and_sleep(&mut self, t_work: Duration) {
+        if self.window.len() == WINDOW_SIZE {
+            self.window.pop_front();
+        }
+        self.window.push_back(t_work);
+
+        let tranquility = self.tranquility();
+        if tranquility <= 0.0 {
+            return;
+        }
+
+        let total: Duration = self.window.iter().sum();
+        let avg = total / self.window.len() as u32;
+        thread::sleep(avg.mul_f64(tranquility));
+    }
+}
+
+impl Default for TranquilityThrottle {
+    fn default() -> TranquilityThrottle {
+        TranquilityThrottle::new(0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flat_out_when_zero() {
+        let mut throttle = TranquilityThrottle::new(0.0);
+        let start = std::time::Instant::now();
+        throttle.observe_and_sleep(Duration::from_millis(50));
+        assert!(start.elapsed() < Duration::from_millis(20));
+    }
+
+    #[test]
+    fn sleeps_proportional_to_tranquility() {
+        let mut throttle = TranquilityThrottle::new(2.0);
+        let start = std::time::Instant::now();
+        throttle.observe_and_sleep(Duration::from_millis(20));
+        assert!(start.elapsed() >= Duration::from_millis(35));
+    }
+
+    #[test]
+    fn window_smooths_spikes() {
+        let mut throttle = TranquilityThrottle::new(1.0);
+        for _ in 0..WINDOW_SIZE {
+            throttle.observe_and_sleep(Duration::from_millis(1));
+        }
+        assert_eq!(throttle.window.len(), WINDOW_SIZE);
+        let start = std::time::Instant::now();
+        throttle.observe_and_sleep(Duration::from_millis(200));
+        // One slow sample among `WINDOW_SIZE` should not cause a ~200ms
+        // sleep; the moving average pulls it down substantially.
+        assert!(start.elapsed() < Duration::from_millis(100));
+    }
+
+    #[test]
+    fn live_reload_takes_effect_next_sleep() {
+        let mut throttle = TranquilityThrottle::new(0.0);
+        assert_eq!(throttle.tranquility(), 0.0);
+        throttle.set_tranquility(1.5);
+        assert_eq!(throttle.tranquility(), 1.5);
+        let start = std::time::Instant::now();
+        throttle.observe_and_sleep(Duration::from_millis(20));
+        assert!(start.elapsed() >= Duration::from_millis(25));
+    }
+}
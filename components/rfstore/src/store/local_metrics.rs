@@ -0,0 +1,62 @@
+// Copyright 2021 TiKV Project Authors. Licensed under Apache-2.0.
+
+//! Periodic collection of runtime metrics that don't fit the per-request
+//! counters in `metrics.rs` on their own, namely allocator-level memory
+//! stats.
+//!
+//! The `jemalloc` arm below requires this crate's `Cargo.toml` to declare a
+//! `jemalloc` feature gating an optional `jemalloc-ctl` dependency, the same
+//! way `tikv_alloc` gates its own jemalloc support; without that the
+//! `#[cfg(feature = "jemalloc")]` module is always compiled out and
+//! `collect_jemalloc_stats` silently no-ops.
+
+#[cfg(feature = "jemalloc")]
+mod jemalloc_metrics {
+    use jemalloc_ctl::{epoch, stats};
+    use tikv_util::warn;
+
+    use crate::store::metrics::{
+        JEMALLOC_ACTIVE_BYTES, JEMALLOC_ALLOCATED_BYTES, JEMALLOC_MAPPED_BYTES,
+        JEMALLOC_RESIDENT_BYTES, JEMALLOC_RETAINED_BYTES,
+    };
+
+    /// Advance jemalloc's stats epoch and publish `active`, `allocated`,
+    /// `resident`, `mapped`, and `retained` byte counts as gauges, so
+    /// operators can correlate raftstore memory pressure (large snapshots,
+    /// apply backlog) with real allocator residency rather than only RSS.
+    pub fn collect() {
+        let advance = epoch::mib().and_then(|mib| mib.advance());
+        if let Err(e) = advance {
+            warn!("failed to advance jemalloc stats epoch"; "err" => ?e);
+            return;
+        }
+
+        macro_rules! publish {
+            ($stat:ident, $gauge:expr) => {
+                match stats::$stat::mib().and_then(|mib| mib.read()) {
+                    Ok(value) => $gauge.set(value as i64),
+                    Err(e) => warn!("failed to read jemalloc stat"; "stat" => stringify!($stat), "err" => ?e),
+                }
+            };
+        }
+
+        publish!(active, JEMALLOC_ACTIVE_BYTES);
+        publish!(allocated, JEMALLOC_ALLOCATED_BYTES);
+        publish!(resident, JEMALLOC_RESIDENT_BYTES);
+        publish!(mapped, JEMALLOC_MAPPED_BYTES);
+        publish!(retained, JEMALLOC_RETAINED_BYTES);
+    }
+}
+
+#[cfg(not(feature = "jemalloc"))]
+mod jemalloc_metrics {
+    /// No-op when TiKV is built against the system allocator, which exposes
+    /// none of these counters.
+    pub fn collect() {}
+}
+
+/// Refresh the allocator memory gauges. Cheap enough to call from a regular
+/// store tick (see `ticker.rs`); a no-op build against the system allocator.
+pub fn collect_jemalloc_stats() {
+    jemalloc_metrics::collect()
+}
@@ -0,0 +1,54 @@
+// Copyright 2021 TiKV Project Authors. Licensed under Apache-2.0.
+
+//! Prometheus metrics for the raftstore.
+
+use lazy_static::lazy_static;
+use prometheus::*;
+
+lazy_static! {
+    /// Number of region scans where the scrub worker found a checksum that
+    /// differs from the region's write-time baseline.
+    pub static ref SCRUB_MISMATCH_COUNTER: IntCounter = register_int_counter!(
+        "tikv_raftstore_scrub_mismatch_total",
+        "Total number of checksum mismatches detected by scrub region scans"
+    )
+    .unwrap();
+
+    /// jemalloc `stats.active`: bytes in active pages allocated by the
+    /// application.
+    pub static ref JEMALLOC_ACTIVE_BYTES: IntGauge = register_int_gauge!(
+        "tikv_allocator_active_bytes",
+        "Bytes in active pages allocated by the allocator"
+    )
+    .unwrap();
+
+    /// jemalloc `stats.allocated`: bytes allocated by the application.
+    pub static ref JEMALLOC_ALLOCATED_BYTES: IntGauge = register_int_gauge!(
+        "tikv_allocator_allocated_bytes",
+        "Bytes allocated by the application"
+    )
+    .unwrap();
+
+    /// jemalloc `stats.resident`: bytes in physically resident pages.
+    pub static ref JEMALLOC_RESIDENT_BYTES: IntGauge = register_int_gauge!(
+        "tikv_allocator_resident_bytes",
+        "Bytes in physically resident data pages mapped by the allocator"
+    )
+    .unwrap();
+
+    /// jemalloc `stats.mapped`: bytes in active extents mapped by the
+    /// allocator.
+    pub static ref JEMALLOC_MAPPED_BYTES: IntGauge = register_int_gauge!(
+        "tikv_allocator_mapped_bytes",
+        "Bytes in active extents mapped by the allocator"
+    )
+    .unwrap();
+
+    /// jemalloc `stats.retained`: bytes held by the allocator but not
+    /// mapped, i.e. unmapped but not released back to the OS.
+    pub static ref JEMALLOC_RETAINED_BYTES: IntGauge = register_int_gauge!(
+        "tikv_allocator_retained_bytes",
+        "Bytes held by the allocator but not mapped by the operating system"
+    )
+    .unwrap();
+}
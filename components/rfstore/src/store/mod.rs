@@ -22,6 +22,7 @@ pub mod read;
 pub mod recover;
 pub mod region_snapshot;
 pub mod rlog;
+pub mod scrub;
 pub mod server;
 pub mod state;
 pub mod store_fsm;
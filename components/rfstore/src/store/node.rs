@@ -0,0 +1,103 @@
+// Copyright 2021 TiKV Project Authors. Licensed under Apache-2.0.
+
+//! Store-level startup wiring: registers the raftstore pieces that need to
+//! be live-reloadable with the shared [`ConfigController`], the same way
+//! `resource-metering.*` is registered when a store starts, and starts the
+//! store's own background workers.
+//!
+//! [`start_store`] is the entry point a real store startup sequence
+//! (`server.rs` / `store_fsm.rs` in a full raftstore, neither of which this
+//! tree carries yet) is meant to call once, after the engines are opened and
+//! before the store starts serving traffic.
+
+use std::thread;
+use std::time::Duration;
+
+use crossbeam::channel::{unbounded, Sender};
+use engine_traits::{KvEngine, RaftEngine};
+use kvproto::metapb::Region;
+use tikv::config::{ConfigController, Module};
+
+use crate::store::config::{ConfigManager, SCRUB_WORKER_NAME};
+use crate::store::engine::Engines;
+use crate::store::pd_handler::PdHandlerWorker;
+use crate::store::peer_worker::{PeerMsg, PeerWorkerRunner};
+use crate::store::recover::RecoverWorker;
+use crate::store::scrub::ScrubWorker;
+use crate::store::ticker::StoreTicker;
+use crate::store::worker::{WorkerRegistry, WorkerState};
+
+/// Register the raftstore [`ConfigManager`](crate::store::config::ConfigManager)
+/// (currently just `tranquility`) with `controller`. Call this once during
+/// store startup, before spawning any background worker through `registry`,
+/// so `raftstore.tranquility` updates reach the running workers.
+pub fn register_raftstore_config(controller: &ConfigController, registry: WorkerRegistry) {
+    controller.register(Module::Raftstore, Box::new(ConfigManager::new(registry)));
+}
+
+/// Start the store's own ticks (PD heartbeat, snapshot GC, allocator stats,
+/// ...) as a background worker. Call once during store startup, alongside
+/// [`register_raftstore_config`].
+pub fn start_store_ticker(registry: &WorkerRegistry) {
+    registry.spawn(Box::new(StoreTicker::new("store-ticker")));
+}
+
+/// Everything [`start_store`] hands back to the rest of store startup: the
+/// registry every background worker was spawned into, and the sending half
+/// of the queue [`PeerWorkerRunner`] drains (the batch system in `peer_fsm`,
+/// absent from this tree, is the intended producer).
+pub struct StoreWorkers {
+    pub registry: WorkerRegistry,
+    pub peer_msg_tx: Sender<PeerMsg>,
+}
+
+/// Bring up every raftstore background worker for a single store: register
+/// live-reloadable config first, then recovery, then the steady-state
+/// workers, under the names the rest of the store already assumes them to
+/// have (e.g. [`SCRUB_WORKER_NAME`] for the scrub worker, so
+/// `raftstore.tranquility` reloads actually reach a running worker). The
+/// scrub worker starts at a tranquility of `1.0`, the `Config` default,
+/// whatever value is configured; only later `raftstore.tranquility` changes
+/// reach it.
+///
+/// `regions` lists the regions this store already has on disk at startup,
+/// recovered before anything else runs; `list_regions` is a live callback
+/// the scrub worker polls afterwards to keep following region splits/merges.
+pub fn start_store<K: KvEngine, R: RaftEngine>(
+    controller: &ConfigController,
+    engines: Engines<K, R>,
+    regions: Vec<Region>,
+    list_regions: Box<dyn Fn() -> Vec<Region> + Send>,
+) -> StoreWorkers {
+    let registry = WorkerRegistry::new();
+    register_raftstore_config(controller, registry.clone());
+
+    let region_ids = regions.iter().map(|r| r.get_id()).collect();
+    registry.spawn(Box::new(RecoverWorker::new("recover", region_ids)));
+    while matches!(
+        registry.get("recover").map(|s| s.state),
+        Some(WorkerState::Active) | Some(WorkerState::Idle)
+    ) {
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    registry.spawn_throttled(
+        Box::new(ScrubWorker::new(
+            SCRUB_WORKER_NAME,
+            engines.kv,
+            "default",
+            list_regions,
+        )),
+        1.0,
+    );
+    registry.spawn(Box::new(PdHandlerWorker::new("pd-worker")));
+    start_store_ticker(&registry);
+
+    let (peer_msg_tx, peer_msg_rx) = unbounded();
+    registry.spawn(Box::new(PeerWorkerRunner::new("peer-worker-0", peer_msg_rx)));
+
+    StoreWorkers {
+        registry,
+        peer_msg_tx,
+    }
+}
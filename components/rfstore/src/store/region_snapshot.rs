@@ -0,0 +1,52 @@
+// Copyright 2021 TiKV Project Authors. Licensed under Apache-2.0.
+
+//! A point-in-time, region-scoped view over a KV engine snapshot.
+
+use engine_traits::{IterOptions, Iterable, Iterator as EngineIterator, Result, Snapshot};
+use kvproto::metapb::Region;
+
+/// A [`Snapshot`] clipped to a single region's key range, used by readers
+/// (`read.rs`) and by maintenance tasks such as the scrub worker that need
+/// to walk exactly the keys a region owns.
+pub struct RegionSnapshot<S: Snapshot> {
+    snapshot: S,
+    region: Region,
+}
+
+impl<S: Snapshot> RegionSnapshot<S> {
+    pub fn new(snapshot: S, region: Region) -> RegionSnapshot<S> {
+        RegionSnapshot { snapshot, region }
+    }
+
+    pub fn region(&self) -> &Region {
+        &self.region
+    }
+
+    pub fn start_key(&self) -> &[u8] {
+        self.region.get_start_key()
+    }
+
+    pub fn end_key(&self) -> &[u8] {
+        self.region.get_end_key()
+    }
+
+    /// Visit every key/value pair in `cf` within this region's range, in
+    /// key order.
+    pub fn for_each_in_cf(&self, cf: &str, mut f: impl FnMut(&[u8], &[u8])) -> Result<()> {
+        let mut opts = IterOptions::default();
+        if !self.start_key().is_empty() {
+            opts.set_lower_bound(self.start_key(), 0);
+        }
+        if !self.end_key().is_empty() {
+            opts.set_upper_bound(self.end_key(), 0);
+        }
+
+        let mut iter = self.snapshot.iterator_opt(cf, opts)?;
+        let mut valid = iter.seek_to_first()?;
+        while valid {
+            f(iter.key(), iter.value());
+            valid = iter.next()?;
+        }
+        Ok(())
+    }
+}
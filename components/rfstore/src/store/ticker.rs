@@ -0,0 +1,94 @@
+// Copyright 2021 TiKV Project Authors. Licensed under Apache-2.0.
+
+//! Periodic store-level ticks that aren't tied to a specific peer.
+
+use std::{collections::HashMap, time::{Duration, Instant}};
+
+use crate::store::local_metrics::collect_jemalloc_stats;
+use crate::store::worker::{BackgroundWorker, WorkerState};
+
+/// Kinds of periodic work the store schedules for itself, independent of
+/// any single peer's raft ticks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum StoreTick {
+    PdStoreHeartbeat,
+    SnapGc,
+    CompactLockCf,
+    ConsistencyCheck,
+    /// Refresh the allocator memory gauges in `metrics.rs`.
+    JemallocStats,
+}
+
+impl StoreTick {
+    const ALL: [StoreTick; 5] = [
+        StoreTick::PdStoreHeartbeat,
+        StoreTick::SnapGc,
+        StoreTick::CompactLockCf,
+        StoreTick::ConsistencyCheck,
+        StoreTick::JemallocStats,
+    ];
+
+    /// Default interval between two ticks of this kind.
+    pub fn interval(self) -> Duration {
+        match self {
+            StoreTick::PdStoreHeartbeat => Duration::from_secs(10),
+            StoreTick::SnapGc => Duration::from_secs(60),
+            StoreTick::CompactLockCf => Duration::from_secs(60),
+            StoreTick::ConsistencyCheck => Duration::from_secs(45 * 60),
+            StoreTick::JemallocStats => Duration::from_secs(10),
+        }
+    }
+}
+
+/// Run the store-owned handling for a single tick. Ticks that belong to a
+/// more specific subsystem (PD heartbeats, GC, ...) are left as no-ops here
+/// until that subsystem registers real handling; allocator stats collection
+/// has no other home, so it runs unconditionally.
+fn handle_store_tick(tick: StoreTick) {
+    if tick == StoreTick::JemallocStats {
+        collect_jemalloc_stats();
+    }
+}
+
+/// Drives every [`StoreTick`] on its own schedule.
+///
+/// Registered into the [`WorkerRegistry`](crate::store::worker::WorkerRegistry)
+/// like any other background worker (see `node.rs`), which makes this the
+/// store's actual tick loop rather than a set of handlers nothing calls.
+pub struct StoreTicker {
+    name: String,
+    last_fired: HashMap<StoreTick, Instant>,
+}
+
+impl StoreTicker {
+    pub fn new(name: impl Into<String>) -> StoreTicker {
+        let now = Instant::now();
+        StoreTicker {
+            name: name.into(),
+            last_fired: StoreTick::ALL.iter().map(|&t| (t, now)).collect(),
+        }
+    }
+}
+
+impl BackgroundWorker for StoreTicker {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn work(&mut self) -> WorkerState {
+        let mut fired_any = false;
+        for tick in StoreTick::ALL {
+            let last = self.last_fired[&tick];
+            if last.elapsed() >= tick.interval() {
+                handle_store_tick(tick);
+                self.last_fired.insert(tick, Instant::now());
+                fired_any = true;
+            }
+        }
+        if fired_any {
+            WorkerState::Active
+        } else {
+            WorkerState::Idle
+        }
+    }
+}
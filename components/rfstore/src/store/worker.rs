@@ -0,0 +1,276 @@
+// Copyright 2021 TiKV Project Authors. Licensed under Apache-2.0.
+
+//! A small registry for the long-running background workers spawned by the
+//! raftstore (peer apply, PD heartbeats, log recovery, and friends).
+//!
+//! Each worker implements [`BackgroundWorker`] and is driven by a dedicated
+//! thread that repeatedly calls [`BackgroundWorker::work`]. The registry
+//! keeps a shared snapshot of every worker's last known state so that
+//! operators can list workers and tell an actively-running one from an idle
+//! one or one that died with an error, without having to grep logs.
+
+use std::{
+    collections::HashMap,
+    panic::{self, AssertUnwindSafe},
+    sync::{Arc, Mutex},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use tikv_util::{error, info, warn};
+
+use crate::store::io_limiter::TranquilityThrottle;
+
+/// Result of a single [`BackgroundWorker::work`] call.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WorkerState {
+    /// The worker did useful work and should be polled again immediately.
+    Active,
+    /// The worker had nothing to do this step and may be polled after a
+    /// short backoff.
+    Idle,
+    /// The worker has finished its job for good and does not need to run
+    /// again.
+    Done,
+    /// The worker hit an unrecoverable error and stopped; the string is a
+    /// human-readable description of what went wrong.
+    Dead(String),
+}
+
+/// A long-running background task managed by the [`WorkerRegistry`].
+pub trait BackgroundWorker: Send {
+    /// Stable, unique name used to identify the worker in introspection
+    /// output.
+    fn name(&self) -> &str;
+
+    /// Perform one step of work and report the resulting state.
+    fn work(&mut self) -> WorkerState;
+
+    /// Optional free-form progress string (e.g. "region 42, key abc..").
+    fn status(&self) -> Option<String> {
+        None
+    }
+}
+
+/// Point-in-time view of a registered worker, as returned by the
+/// introspection API.
+#[derive(Debug, Clone)]
+pub struct WorkerSnapshot {
+    pub name: String,
+    pub state: WorkerState,
+    pub last_error: Option<String>,
+    pub progress: Option<String>,
+    pub paused: bool,
+}
+
+/// How long an idle or paused worker sleeps before being polled again.
+const IDLE_BACKOFF: Duration = Duration::from_millis(200);
+
+/// Operator-controlled state for a running worker, set through
+/// [`WorkerRegistry::pause`] / [`WorkerRegistry::resume`] /
+/// [`WorkerRegistry::cancel`] and read back by the worker's own thread.
+/// Starting a worker is just [`WorkerRegistry::spawn`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ControlFlag {
+    Running,
+    Paused,
+    Cancelled,
+}
+
+struct WorkerSlot {
+    snapshot: Mutex<WorkerSnapshot>,
+    handle: Mutex<Option<JoinHandle<()>>>,
+    throttle: Arc<TranquilityThrottle>,
+    control: Mutex<ControlFlag>,
+}
+
+/// Registry of all background workers spawned by a raftstore instance.
+///
+/// Workers register themselves via [`WorkerRegistry::spawn`]; the registry
+/// then owns the thread that drives them and exposes [`WorkerRegistry::list`]
+/// / [`WorkerRegistry::get`] for runtime introspection (e.g. a debug RPC or
+/// `tikv-ctl` command backing a "list workers" operation).
+#[derive(Clone, Default)]
+pub struct WorkerRegistry {
+    slots: Arc<Mutex<HashMap<String, Arc<WorkerSlot>>>>,
+}
+
+impl WorkerRegistry {
+    pub fn new() -> WorkerRegistry {
+        WorkerRegistry::default()
+    }
+
+    /// Register `worker` and start driving it on its own thread, with no
+    /// tranquility throttling (the worker runs flat out between idle
+    /// backoffs).
+    ///
+    /// Panics from `work()` are caught and recorded as [`WorkerState::Dead`]
+    /// instead of taking down the thread silently.
+    pub fn spawn(&self, worker: Box<dyn BackgroundWorker>) {
+        self.spawn_throttled(worker, 0.0)
+    }
+
+    /// Like [`WorkerRegistry::spawn`], but self-throttle the worker's duty
+    /// cycle: after each `work()` call, sleep for `t_work * tranquility`
+    /// (averaged over a small window of recent iterations), where `t_work`
+    /// is how long that call took. `tranquility` can be live-reloaded later
+    /// via [`WorkerRegistry::set_tranquility`].
+    pub fn spawn_throttled(&self, mut worker: Box<dyn BackgroundWorker>, tranquility: f64) {
+        let name = worker.name().to_owned();
+        let slot = Arc::new(WorkerSlot {
+            snapshot: Mutex::new(WorkerSnapshot {
+                name: name.clone(),
+                state: WorkerState::Idle,
+                last_error: None,
+                progress: None,
+                paused: false,
+            }),
+            handle: Mutex::new(None),
+            throttle: Arc::new(TranquilityThrottle::new(tranquility)),
+            control: Mutex::new(ControlFlag::Running),
+        });
+        self.slots.lock().unwrap().insert(name.clone(), slot.clone());
+
+        // `slot` is also needed after the thread is spawned (to stash the
+        // `JoinHandle`), so the closure gets its own clone rather than the
+        // original.
+        let thread_slot = slot.clone();
+        let handle = thread::Builder::new()
+            .name(format!("bg-worker-{}", name))
+            .spawn(move || {
+                info!("background worker started"; "name" => %name);
+                let mut throttle = TranquilityThrottle::new(thread_slot.throttle.tranquility());
+                loop {
+                    match *thread_slot.control.lock().unwrap() {
+                        ControlFlag::Cancelled => {
+                            let mut snapshot = thread_slot.snapshot.lock().unwrap();
+                            snapshot.state = WorkerState::Done;
+                            snapshot.paused = false;
+                            drop(snapshot);
+                            info!("background worker cancelled"; "name" => %name);
+                            break;
+                        }
+                        ControlFlag::Paused => {
+                            thread_slot.snapshot.lock().unwrap().paused = true;
+                            thread::sleep(IDLE_BACKOFF);
+                            continue;
+                        }
+                        ControlFlag::Running => {
+                            thread_slot.snapshot.lock().unwrap().paused = false;
+                        }
+                    }
+
+                    let start = Instant::now();
+                    let result = panic::catch_unwind(AssertUnwindSafe(|| worker.work()));
+                    let t_work = start.elapsed();
+                    let progress = worker.status();
+                    let state = match result {
+                        Ok(state) => state,
+                        Err(payload) => {
+                            let msg = panic_message(payload);
+                            error!("background worker panicked"; "name" => %name, "err" => %msg);
+                            WorkerState::Dead(msg)
+                        }
+                    };
+
+                    let mut snapshot = thread_slot.snapshot.lock().unwrap();
+                    snapshot.progress = progress;
+                    if let WorkerState::Dead(ref err) = state {
+                        snapshot.last_error = Some(err.clone());
+                    }
+                    snapshot.state = state.clone();
+                    drop(snapshot);
+
+                    match state {
+                        WorkerState::Active => {
+                            throttle.set_tranquility(thread_slot.throttle.tranquility());
+                            throttle.observe_and_sleep(t_work);
+                        }
+                        WorkerState::Idle => {
+                            // An idle step has ~0 useful work in it, so
+                            // feeding `t_work` into the throttle would both
+                            // sleep for ~0 (hot-spinning instead of backing
+                            // off) and dilute the moving window, under-
+                            // throttling the next bout of real work. Idle
+                            // always backs off by a fixed amount and leaves
+                            // the window alone.
+                            thread::sleep(IDLE_BACKOFF);
+                        }
+                        WorkerState::Done => {
+                            info!("background worker finished"; "name" => %name);
+                            break;
+                        }
+                        WorkerState::Dead(err) => {
+                            warn!("background worker died"; "name" => %name, "err" => %err);
+                            break;
+                        }
+                    }
+                }
+            })
+            .unwrap();
+        *slot.handle.lock().unwrap() = Some(handle);
+    }
+
+    /// Live-reload the tranquility factor for an already-running worker.
+    /// Takes effect starting with its next sleep.
+    pub fn set_tranquility(&self, name: &str, tranquility: f64) {
+        if let Some(slot) = self.slots.lock().unwrap().get(name) {
+            slot.throttle.set_tranquility(tranquility);
+        }
+    }
+
+    /// Stop calling `work()` on `name` until [`WorkerRegistry::resume`] is
+    /// called. The worker thread keeps running (so it can still be resumed)
+    /// but sits idle.
+    pub fn pause(&self, name: &str) {
+        self.set_control(name, ControlFlag::Paused);
+    }
+
+    /// Resume a worker previously paused with [`WorkerRegistry::pause`].
+    pub fn resume(&self, name: &str) {
+        self.set_control(name, ControlFlag::Running);
+    }
+
+    /// Permanently stop a worker; its next poll reports [`WorkerState::Done`]
+    /// and its thread exits. There is no way to restart it other than
+    /// registering a fresh worker under the same name.
+    pub fn cancel(&self, name: &str) {
+        self.set_control(name, ControlFlag::Cancelled);
+    }
+
+    fn set_control(&self, name: &str, flag: ControlFlag) {
+        if let Some(slot) = self.slots.lock().unwrap().get(name) {
+            *slot.control.lock().unwrap() = flag;
+        }
+    }
+
+    /// Return a snapshot of every registered worker, for an operator-facing
+    /// "list workers" view.
+    pub fn list(&self) -> Vec<WorkerSnapshot> {
+        self.slots
+            .lock()
+            .unwrap()
+            .values()
+            .map(|slot| slot.snapshot.lock().unwrap().clone())
+            .collect()
+    }
+
+    /// Return the current snapshot of a single worker by name.
+    pub fn get(&self, name: &str) -> Option<WorkerSnapshot> {
+        self.slots
+            .lock()
+            .unwrap()
+            .get(name)
+            .map(|slot| slot.snapshot.lock().unwrap().clone())
+    }
+}
+
+fn panic_message(payload: Box<dyn std::any::Any + Send>) -> String {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        (*s).to_owned()
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "worker panicked with an unknown payload".to_owned()
+    }
+}
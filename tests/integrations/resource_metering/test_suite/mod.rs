@@ -22,6 +22,26 @@ use tikv::storage::{RocksEngine, Storage, TestEngineBuilder, TestStorageBuilder}
 use tokio::runtime::{self, Runtime};
 use txn_types::{Key, TimeStamp};
 
+/// Per-resource-group-tag usage aggregated across a batch of
+/// `ResourceUsageRecord`s, parallel-indexed by `timestamp_sec`. CPU time
+/// alone hides a tenant that scans heavily but burns little CPU, so read
+/// and write key counts are tracked alongside it.
+///
+/// `ResourceUsageRecord` only carries key-granularity counters
+/// (`record_list_read_keys` / `record_list_write_keys`); byte-granularity
+/// accounting would need those fields added to the `kvproto` message first.
+/// `resource_metering::ResourceTagFactory`/`ResourceMeteringTag` (see that
+/// crate's `recorder` module) are what `tikv::storage`'s `get`/scan path
+/// calls to credit read/write keys to a tag; this struct only needs to
+/// carry whatever the recorder ends up reporting.
+#[derive(Default, Debug)]
+pub struct ResourceUsageSummary {
+    pub timestamp_sec: Vec<u64>,
+    pub cpu_time_ms: Vec<u32>,
+    pub read_keys: Vec<u32>,
+    pub write_keys: Vec<u32>,
+}
+
 pub struct TestSuite {
     receiver_server: Option<MockReceiverServer>,
 
@@ -196,7 +216,7 @@ impl TestSuite {
         }
     }
 
-    pub fn nonblock_receiver_all(&self) -> HashMap<String, (Vec<u64>, Vec<u32>)> {
+    pub fn nonblock_receiver_all(&self) -> HashMap<String, ResourceUsageSummary> {
         let mut res = HashMap::new();
         for r in self.rx.try_recv() {
             Self::merge_records(&mut res, r);
@@ -204,7 +224,7 @@ impl TestSuite {
         res
     }
 
-    pub fn block_receive_one(&self) -> HashMap<String, (Vec<u64>, Vec<u32>)> {
+    pub fn block_receive_one(&self) -> HashMap<String, ResourceUsageSummary> {
         let records = self.rx.recv().unwrap();
         let mut res = HashMap::new();
         Self::merge_records(&mut res, records);
@@ -212,14 +232,16 @@ impl TestSuite {
     }
 
     fn merge_records(
-        map: &mut HashMap<String, (Vec<u64>, Vec<u32>)>,
+        map: &mut HashMap<String, ResourceUsageSummary>,
         records: Vec<ResourceUsageRecord>,
     ) {
         for r in records {
             let tag = String::from_utf8_lossy(r.get_resource_group_tag()).into_owned();
-            let (ts, cpu_time) = map.entry(tag).or_insert((vec![], vec![]));
-            ts.extend(&r.record_list_timestamp_sec);
-            cpu_time.extend(&r.record_list_cpu_time_ms);
+            let summary = map.entry(tag).or_insert_with(ResourceUsageSummary::default);
+            summary.timestamp_sec.extend(&r.record_list_timestamp_sec);
+            summary.cpu_time_ms.extend(&r.record_list_cpu_time_ms);
+            summary.read_keys.extend(&r.record_list_read_keys);
+            summary.write_keys.extend(&r.record_list_write_keys);
         }
     }
 
@@ -235,4 +257,65 @@ impl Drop for TestSuite {
     fn drop(&mut self) {
         self.stop_workers.take().unwrap()();
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(
+        tag: &str,
+        cpu_time_ms: Vec<u32>,
+        read_keys: Vec<u32>,
+        write_keys: Vec<u32>,
+    ) -> ResourceUsageRecord {
+        let mut r = ResourceUsageRecord::default();
+        r.resource_group_tag = tag.as_bytes().to_vec();
+        r.record_list_timestamp_sec = (0..cpu_time_ms.len() as u64).collect();
+        r.record_list_cpu_time_ms = cpu_time_ms;
+        r.record_list_read_keys = read_keys;
+        r.record_list_write_keys = write_keys;
+        r
+    }
+
+    #[test]
+    fn merge_records_tracks_scan_heavy_low_cpu_tenant() {
+        let mut map = HashMap::new();
+        // Barely uses CPU but reads tens of thousands of keys per interval;
+        // a CPU-time-only profiler would make this tenant invisible.
+        let scanner = record("scanner", vec![1, 1], vec![20_000, 18_000], vec![0, 0]);
+        // CPU-heavy but touches very few keys.
+        let writer = record("writer", vec![500, 480], vec![2, 3], vec![5, 4]);
+
+        TestSuite::merge_records(&mut map, vec![scanner, writer]);
+
+        let scanner_usage = &map["scanner"];
+        assert_eq!(scanner_usage.cpu_time_ms, vec![1, 1]);
+        assert_eq!(scanner_usage.read_keys, vec![20_000, 18_000]);
+        assert_eq!(scanner_usage.write_keys, vec![0, 0]);
+
+        let writer_usage = &map["writer"];
+        assert_eq!(writer_usage.cpu_time_ms, vec![500, 480]);
+        assert_eq!(writer_usage.read_keys, vec![2, 3]);
+        assert_eq!(writer_usage.write_keys, vec![5, 4]);
+
+        // The dimension that was previously invisible: low CPU, high reads.
+        let scanner_total_reads: u32 = scanner_usage.read_keys.iter().sum();
+        let scanner_total_cpu: u32 = scanner_usage.cpu_time_ms.iter().sum();
+        let writer_total_cpu: u32 = writer_usage.cpu_time_ms.iter().sum();
+        let writer_total_reads: u32 = writer_usage.read_keys.iter().sum();
+        assert!(scanner_total_cpu < writer_total_cpu);
+        assert!(scanner_total_reads > writer_total_reads);
+    }
+
+    #[test]
+    fn merge_records_accumulates_across_batches() {
+        let mut map = HashMap::new();
+        TestSuite::merge_records(&mut map, vec![record("tenant", vec![1], vec![100], vec![0])]);
+        TestSuite::merge_records(&mut map, vec![record("tenant", vec![2], vec![200], vec![0])]);
+
+        let usage = &map["tenant"];
+        assert_eq!(usage.cpu_time_ms, vec![1, 2]);
+        assert_eq!(usage.read_keys, vec![100, 200]);
+    }
 }
\ No newline at end of file